@@ -0,0 +1,69 @@
+//! Rendering of command output as a table, JSON, or CSV.
+
+use anyhow::Result;
+use prettytable::{cell, format, row, Table};
+use serde::Serialize;
+use structopt::clap::arg_enum;
+
+use crate::{Entry, Subscription};
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum OutputFormat {
+        Table,
+        Json,
+        Csv,
+    }
+}
+
+fn print_csv<T: Serialize>(rows: &[T]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+
+    for row in rows {
+        writer.serialize(row)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn render_entries(entries: &[Entry], format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+            table.set_titles(row!["url", "title"]);
+
+            for entry in entries {
+                table.add_row(row![entry.full_url, entry.title]);
+            }
+
+            table.printstd();
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(entries)?),
+        OutputFormat::Csv => print_csv(entries)?,
+    }
+
+    Ok(())
+}
+
+pub fn render_subscriptions(subscriptions: &[Subscription], format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+            table.set_titles(row!["url"]);
+
+            for subscription in subscriptions {
+                table.add_row(row![subscription.url]);
+            }
+
+            table.printstd();
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(subscriptions)?),
+        OutputFormat::Csv => print_csv(subscriptions)?,
+    }
+
+    Ok(())
+}
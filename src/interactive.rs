@@ -0,0 +1,173 @@
+//! Interactive TUI for browsing and marking unread entries over one
+//! persistent connection.
+
+use std::io;
+use std::time::Duration;
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use tui::backend::CrosstermBackend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::style::{Modifier, Style};
+use tui::text::Span;
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use tui::Terminal;
+
+use crate::transport::Transport;
+use crate::{authenticate, fetch_unread_entries, mark_read, Entry};
+
+enum Action {
+    Continue,
+    Quit,
+}
+
+struct State {
+    entries: Vec<Entry>,
+    selected: ListState,
+    status: String,
+}
+
+impl State {
+    fn new(entries: Vec<Entry>) -> Self {
+        let mut selected = ListState::default();
+        if !entries.is_empty() {
+            selected.select(Some(0));
+        }
+
+        Self {
+            entries,
+            selected,
+            status: String::from(
+                "j/k or arrows to move, enter to open, m to mark read, r to refresh, q to quit",
+            ),
+        }
+    }
+
+    fn clamp_selection(&mut self) {
+        if self.entries.is_empty() {
+            self.selected.select(None);
+            return;
+        }
+
+        let index = self
+            .selected
+            .selected()
+            .unwrap_or(0)
+            .min(self.entries.len() - 1);
+        self.selected.select(Some(index));
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+
+        let len = self.entries.len() as isize;
+        let current = self.selected.selected().unwrap_or(0) as isize;
+        let next = (current + delta).rem_euclid(len);
+        self.selected.select(Some(next as usize));
+    }
+}
+
+async fn handle_key(
+    transport: &mut impl Transport,
+    key: KeyCode,
+    state: &mut State,
+) -> Result<Action> {
+    match key {
+        KeyCode::Char('q') | KeyCode::Esc => return Ok(Action::Quit),
+        KeyCode::Down | KeyCode::Char('j') => state.move_selection(1),
+        KeyCode::Up | KeyCode::Char('k') => state.move_selection(-1),
+        KeyCode::Enter | KeyCode::Char('o') => {
+            if let Some(index) = state.selected.selected() {
+                let url = state.entries[index].full_url.clone();
+                match open::that(&url) {
+                    Ok(()) => state.status = format!("opened {}", url),
+                    Err(err) => state.status = format!("failed to open {}: {}", url, err),
+                }
+            }
+        }
+        KeyCode::Char('m') => {
+            if let Some(index) = state.selected.selected() {
+                let entry_id = state.entries[index].id;
+                mark_read(transport, entry_id).await?;
+                state.status = format!("marked entry {} as read", entry_id);
+                state.entries.remove(index);
+                state.clamp_selection();
+            }
+        }
+        KeyCode::Char('r') => {
+            state.entries = fetch_unread_entries(transport).await?;
+            state.clamp_selection();
+            state.status = format!("refreshed, {} unread", state.entries.len());
+        }
+        _ => {}
+    }
+
+    Ok(Action::Continue)
+}
+
+/// Runs the interactive browsing loop until the user quits.
+pub async fn run(transport: &mut impl Transport, user: String) -> Result<()> {
+    authenticate(transport, user).await?;
+    let entries = fetch_unread_entries(transport).await?;
+
+    let mut state = State::new(entries);
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let result = run_loop(&mut terminal, transport, &mut state).await;
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+async fn run_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    transport: &mut impl Transport,
+    state: &mut State,
+) -> Result<()> {
+    loop {
+        terminal.draw(|frame| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(1)])
+                .split(frame.size());
+
+            let items: Vec<ListItem> = state
+                .entries
+                .iter()
+                .map(|entry| ListItem::new(format!("{}  {}", entry.title, entry.full_url)))
+                .collect();
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Unread"))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+            frame.render_stateful_widget(list, chunks[0], &mut state.selected);
+
+            let status = Paragraph::new(Span::raw(state.status.as_str()));
+            frame.render_widget(status, chunks[1]);
+        })?;
+
+        if event::poll(Duration::from_millis(200))? {
+            if let Event::Key(key) = event::read()? {
+                match handle_key(transport, key.code, state).await? {
+                    Action::Continue => {}
+                    Action::Quit => return Ok(()),
+                }
+            }
+        }
+    }
+}
@@ -2,19 +2,32 @@ use std::net::ToSocketAddrs;
 use std::path::PathBuf;
 
 use anyhow::{format_err, Context, Result};
-use prettytable::{cell, format, row, Table};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use seymour_protocol::{Command, Response};
 use structopt::StructOpt;
-use tokio::io::{
-    AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf,
-};
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::net::TcpStream;
 
+mod format;
+mod interactive;
+mod opml;
+mod tls;
+mod transport;
+
+use format::OutputFormat;
+use tls::MaybeTlsStream;
+use transport::{TcpTransport, Transport};
+
 #[derive(Debug, Deserialize)]
 struct Config {
     host_port: String,
     user: String,
+
+    /// Wrap the connection in TLS, verifying the server cert with
+    /// trust-on-first-use pinning. Defaults to off so existing plaintext
+    /// configs keep working.
+    #[serde(default)]
+    tls: bool,
 }
 
 #[derive(Debug, StructOpt)]
@@ -29,6 +42,23 @@ enum Subcommand {
     /// List all subscriptions
     #[structopt(alias = "subscriptions")]
     ListSubscriptions,
+
+    /// Export all subscriptions as an OPML 2.0 document
+    ExportOpml {
+        /// File to write the OPML document to. Defaults to stdout.
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Subscribe to every feed referenced by an OPML document
+    ImportOpml {
+        /// OPML file to read feed URLs from
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+
+    /// Browse unread entries and mark them read over one persistent connection
+    Interactive,
 }
 
 #[derive(Debug, StructOpt)]
@@ -41,33 +71,33 @@ struct Args {
     #[structopt(long, parse(from_os_str))]
     config_file: Option<PathBuf>,
 
-    #[structopt(subcommand)]
-    subcommand: Subcommand,
-}
+    /// Skip TLS even if the config file has `tls = true`, falling back to a
+    /// plaintext connection.
+    #[structopt(long)]
+    insecure: bool,
 
-async fn send<T: AsyncWrite>(writer: &mut WriteHalf<T>, command: Command) -> Result<()> {
-    Ok(writer
-        .write_all(format!("{}\r\n", command).as_bytes())
-        .await?)
-}
+    /// Output format for listing commands
+    #[structopt(
+        long,
+        possible_values = &OutputFormat::variants(),
+        case_insensitive = true,
+        default_value = "table"
+    )]
+    format: OutputFormat,
 
-async fn receive<T: AsyncBufRead + Unpin>(lines: &mut Lines<T>) -> Result<Response> {
-    Ok(lines
-        .next_line()
-        .await?
-        .ok_or_else(|| format_err!("no line from server"))?
-        .parse()?)
+    #[structopt(subcommand)]
+    subcommand: Subcommand,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 struct Entry {
     id: i64,
     full_url: String,
     title: String,
 }
 
-#[derive(Debug)]
-struct Subscription {
+#[derive(Debug, Serialize)]
+pub(crate) struct Subscription {
     id: i64,
     url: String,
 }
@@ -87,9 +117,7 @@ macro_rules! check_response {
     };
 }
 
-async fn connect(
-    config: &Config,
-) -> Result<(Lines<BufReader<ReadHalf<TcpStream>>>, WriteHalf<TcpStream>)> {
+async fn connect(config: &Config, insecure: bool) -> Result<TcpTransport> {
     let address = config
         .host_port
         .to_socket_addrs()?
@@ -97,36 +125,38 @@ async fn connect(
         .ok_or_else(|| format_err!("missing server address"))?;
     let stream = TcpStream::connect(&address).await?;
 
+    let stream = if config.tls && !insecure {
+        tls::wrap(stream, &config.host_port).await?
+    } else {
+        MaybeTlsStream::Plain(stream)
+    };
+
     let (reader, writer) = tokio::io::split(stream);
 
     let server_reader = BufReader::new(reader);
     let lines = server_reader.lines();
 
-    Ok((lines, writer))
+    Ok(TcpTransport::new(lines, writer))
 }
 
-async fn cmd_unread(config: Config, no_mark_read: bool) -> Result<()> {
-    let (mut lines, mut writer) = connect(&config).await?;
+async fn authenticate(transport: &mut impl Transport, user: String) -> Result<()> {
+    transport.send(Command::User { username: user }).await?;
 
-    send(
-        &mut writer,
-        Command::User {
-            username: config.user,
-        },
-    )
-    .await?;
-
-    let response: Response = receive(&mut lines).await?;
+    let response = transport.receive().await?;
     check_response!(Response::AckUser { .. }, response);
 
-    send(&mut writer, Command::ListUnread).await?;
+    Ok(())
+}
+
+async fn fetch_unread_entries(transport: &mut impl Transport) -> Result<Vec<Entry>> {
+    transport.send(Command::ListUnread).await?;
 
-    let response: Response = receive(&mut lines).await?;
+    let response = transport.receive().await?;
     check_response!(Response::StartEntryList, response);
 
     let mut entries = Vec::new();
     loop {
-        let response: Response = receive(&mut lines).await?;
+        let response = transport.receive().await?;
         match response {
             Response::Entry {
                 feed_url,
@@ -153,56 +183,84 @@ async fn cmd_unread(config: Config, no_mark_read: bool) -> Result<()> {
         }
     }
 
-    if entries.is_empty() {
-        println!("No new items");
-        return Ok(());
-    }
+    Ok(entries)
+}
 
-    println!("{} new item(s)", entries.len());
+async fn mark_read(transport: &mut impl Transport, id: i64) -> Result<()> {
+    transport.send(Command::MarkRead { id }).await?;
 
-    let mut table = Table::new();
-    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    let response = transport.receive().await?;
+    check_response!(Response::AckMarkRead, response);
 
-    table.set_titles(row!["url", "title"]);
+    Ok(())
+}
 
+/// Marks every entry read in one pipelined round-trip: all `MarkRead`
+/// commands go out before any `AckMarkRead` is read back, so catching up on
+/// N entries costs ~1 RTT instead of N. Assumes the whole batch fits in the
+/// socket/BufWriter buffers on both ends; an unbounded backlog could instead
+/// deadlock with both sides blocked on a full write buffer.
+async fn mark_all_read(transport: &mut impl Transport, entries: &[Entry]) -> Result<()> {
     for entry in entries {
-        table.add_row(row![entry.full_url, entry.title]);
-
-        if !no_mark_read {
-            send(&mut writer, Command::MarkRead { id: entry.id }).await?;
+        transport.send(Command::MarkRead { id: entry.id }).await?;
+    }
 
-            let response: Response = receive(&mut lines).await?;
-            check_response!(Response::AckMarkRead, response);
+    for entry in entries {
+        let response = transport.receive().await?;
+        match response {
+            Response::AckMarkRead => {}
+            _ => {
+                return Err(format_err!(
+                    "unexpected response marking entry {} read (expected AckMarkRead): {}",
+                    entry.id,
+                    response
+                ));
+            }
         }
     }
 
-    table.printstd();
-
     Ok(())
 }
 
-async fn cmd_list_subscriptions(config: Config) -> Result<()> {
-    let (mut lines, mut writer) = connect(&config).await?;
+async fn unread(
+    transport: &mut impl Transport,
+    user: String,
+    no_mark_read: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    authenticate(transport, user).await?;
+
+    let entries = fetch_unread_entries(transport).await?;
+
+    if format == OutputFormat::Table {
+        if entries.is_empty() {
+            println!("No new items");
+        } else {
+            println!("{} new item(s)", entries.len());
+        }
+    }
+
+    if !entries.is_empty() && !no_mark_read {
+        mark_all_read(transport, &entries).await?;
+    }
 
-    send(
-        &mut writer,
-        Command::User {
-            username: config.user,
-        },
-    )
-    .await?;
+    format::render_entries(&entries, format)
+}
 
-    let response: Response = receive(&mut lines).await?;
-    check_response!(Response::AckUser { .. }, response);
+async fn fetch_subscriptions(
+    transport: &mut impl Transport,
+    user: String,
+) -> Result<Vec<Subscription>> {
+    authenticate(transport, user).await?;
 
-    send(&mut writer, Command::ListSubscriptions).await?;
+    transport.send(Command::ListSubscriptions).await?;
 
-    let response: Response = receive(&mut lines).await?;
+    let response = transport.receive().await?;
     check_response!(Response::StartSubscriptionList, response);
 
     let mut subscriptions = Vec::new();
     loop {
-        let response: Response = receive(&mut lines).await?;
+        let response = transport.receive().await?;
         match response {
             Response::Subscription { id, url } => {
                 subscriptions.push(Subscription { id, url });
@@ -219,24 +277,96 @@ async fn cmd_list_subscriptions(config: Config) -> Result<()> {
         }
     }
 
-    if subscriptions.is_empty() {
+    Ok(subscriptions)
+}
+
+async fn list_subscriptions(
+    transport: &mut impl Transport,
+    user: String,
+    format: OutputFormat,
+) -> Result<()> {
+    let subscriptions = fetch_subscriptions(transport, user).await?;
+
+    if subscriptions.is_empty() && format == OutputFormat::Table {
         println!("No subscriptions");
     }
 
-    let mut table = Table::new();
-    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    format::render_subscriptions(&subscriptions, format)
+}
 
-    table.set_titles(row!["url"]);
+async fn export_opml(transport: &mut impl Transport, user: String) -> Result<String> {
+    let subscriptions = fetch_subscriptions(transport, user).await?;
+    opml::export(&subscriptions)
+}
+
+/// Subscribes to every URL in turn, relying on `Command::Subscribe` and
+/// `Response::AckSubscribe` from `seymour_protocol` (present since 0.1.4).
+async fn import_opml(transport: &mut impl Transport, user: String, contents: &str) -> Result<()> {
+    let urls = opml::parse(contents)?;
+
+    authenticate(transport, user).await?;
 
-    for entry in subscriptions {
-        table.add_row(row![entry.url]);
+    let mut succeeded = 0;
+    let mut failed = 0;
+
+    for url in urls {
+        transport.send(Command::Subscribe { url: url.clone() }).await?;
+
+        match transport.receive().await? {
+            Response::AckSubscribe => succeeded += 1,
+            response => {
+                println!("failed to subscribe to {}: {}", url, response);
+                failed += 1;
+            }
+        }
     }
 
-    table.printstd();
+    println!("{} succeeded, {} failed", succeeded, failed);
+
+    Ok(())
+}
+
+async fn cmd_unread(
+    config: Config,
+    no_mark_read: bool,
+    insecure: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut transport = connect(&config, insecure).await?;
+    unread(&mut transport, config.user, no_mark_read, format).await
+}
+
+async fn cmd_list_subscriptions(config: Config, insecure: bool, format: OutputFormat) -> Result<()> {
+    let mut transport = connect(&config, insecure).await?;
+    list_subscriptions(&mut transport, config.user, format).await
+}
+
+async fn cmd_export_opml(config: Config, insecure: bool, output: Option<PathBuf>) -> Result<()> {
+    let mut transport = connect(&config, insecure).await?;
+    let document = export_opml(&mut transport, config.user).await?;
+
+    match output {
+        Some(path) => std::fs::write(&path, document)
+            .with_context(|| format_err!("failed to write OPML document to {:?}", path))?,
+        None => println!("{}", document),
+    }
 
     Ok(())
 }
 
+async fn cmd_import_opml(config: Config, insecure: bool, file: PathBuf) -> Result<()> {
+    let contents = std::fs::read_to_string(&file)
+        .with_context(|| format_err!("failed to read OPML file at {:?}", file))?;
+
+    let mut transport = connect(&config, insecure).await?;
+    import_opml(&mut transport, config.user, &contents).await
+}
+
+async fn cmd_interactive(config: Config, insecure: bool) -> Result<()> {
+    let mut transport = connect(&config, insecure).await?;
+    interactive::run(&mut transport, config.user).await
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::from_args();
@@ -258,7 +388,162 @@ async fn main() -> Result<()> {
     .with_context(|| format_err!("failed to parse config file at {:?}", config_file))?;
 
     match args.subcommand {
-        Subcommand::Unread { no_mark_read } => cmd_unread(config, no_mark_read).await,
-        Subcommand::ListSubscriptions => cmd_list_subscriptions(config).await,
+        Subcommand::Unread { no_mark_read } => {
+            cmd_unread(config, no_mark_read, args.insecure, args.format).await
+        }
+        Subcommand::ListSubscriptions => {
+            cmd_list_subscriptions(config, args.insecure, args.format).await
+        }
+        Subcommand::ExportOpml { output } => {
+            cmd_export_opml(config, args.insecure, output).await
+        }
+        Subcommand::ImportOpml { file } => cmd_import_opml(config, args.insecure, file).await,
+        Subcommand::Interactive => cmd_interactive(config, args.insecure).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transport::MockTransport;
+
+    #[tokio::test]
+    async fn test_unread_lists_and_marks_read() {
+        let mut transport = MockTransport::new(vec![
+            Response::AckUser { id: 1 },
+            Response::StartEntryList,
+            Response::Entry {
+                feed_url: "gemini://example.com/feed".to_string(),
+                feed_id: 1,
+                url: "posts/1.gmi".to_string(),
+                title: "First post".to_string(),
+                id: 1,
+            },
+            Response::Entry {
+                feed_url: "gemini://example.com/feed".to_string(),
+                feed_id: 1,
+                url: "posts/2.gmi".to_string(),
+                title: "Second post".to_string(),
+                id: 2,
+            },
+            Response::EndList,
+            Response::AckMarkRead,
+            Response::AckMarkRead,
+        ]);
+
+        unread(&mut transport, "alice".to_string(), false, OutputFormat::Table)
+            .await
+            .expect("unread should succeed");
+
+        assert_eq!(transport.sent.len(), 4);
+        assert!(matches!(transport.sent[0], Command::User { .. }));
+        assert!(matches!(transport.sent[1], Command::ListUnread));
+        assert!(matches!(transport.sent[2], Command::MarkRead { id: 1 }));
+        assert!(matches!(transport.sent[3], Command::MarkRead { id: 2 }));
+    }
+
+    #[tokio::test]
+    async fn test_unread_no_mark_read_skips_mark_commands() {
+        let mut transport = MockTransport::new(vec![
+            Response::AckUser { id: 1 },
+            Response::StartEntryList,
+            Response::Entry {
+                feed_url: "gemini://example.com/feed".to_string(),
+                feed_id: 1,
+                url: "posts/1.gmi".to_string(),
+                title: "First post".to_string(),
+                id: 1,
+            },
+            Response::EndList,
+        ]);
+
+        unread(&mut transport, "alice".to_string(), true, OutputFormat::Table)
+            .await
+            .expect("unread should succeed");
+
+        assert_eq!(transport.sent.len(), 2);
+        assert!(matches!(transport.sent[1], Command::ListUnread));
+    }
+
+    #[tokio::test]
+    async fn test_unread_unexpected_response_after_user() {
+        let mut transport = MockTransport::new(vec![Response::EndList]);
+
+        let err = unread(&mut transport, "alice".to_string(), false, OutputFormat::Table)
+            .await
+            .expect_err("expected an unexpected-response error");
+
+        assert!(err.to_string().contains("unexpected response"));
+    }
+
+    #[tokio::test]
+    async fn test_unread_unexpected_response_instead_of_entry() {
+        let mut transport = MockTransport::new(vec![
+            Response::AckUser { id: 1 },
+            Response::StartEntryList,
+            Response::StartSubscriptionList,
+        ]);
+
+        let err = unread(&mut transport, "alice".to_string(), false, OutputFormat::Table)
+            .await
+            .expect_err("expected an unexpected-response error");
+
+        assert!(err.to_string().contains("expected Entry or EndList"));
+    }
+
+    #[tokio::test]
+    async fn test_unread_pipelines_mark_read_and_surfaces_unexpected_ack() {
+        let mut transport = MockTransport::new(vec![
+            Response::AckUser { id: 1 },
+            Response::StartEntryList,
+            Response::Entry {
+                feed_url: "gemini://example.com/feed".to_string(),
+                feed_id: 1,
+                url: "posts/1.gmi".to_string(),
+                title: "First post".to_string(),
+                id: 1,
+            },
+            Response::Entry {
+                feed_url: "gemini://example.com/feed".to_string(),
+                feed_id: 1,
+                url: "posts/2.gmi".to_string(),
+                title: "Second post".to_string(),
+                id: 2,
+            },
+            Response::EndList,
+            Response::AckMarkRead,
+            Response::StartSubscriptionList,
+        ]);
+
+        let err = unread(&mut transport, "alice".to_string(), false, OutputFormat::Table)
+            .await
+            .expect_err("expected an unexpected-response error");
+
+        // Both MarkRead commands should have been sent before either
+        // AckMarkRead was read back.
+        assert_eq!(transport.sent.len(), 4);
+        assert!(matches!(transport.sent[2], Command::MarkRead { id: 1 }));
+        assert!(matches!(transport.sent[3], Command::MarkRead { id: 2 }));
+        assert!(err.to_string().contains("unexpected response marking entry 2 read"));
+    }
+
+    #[tokio::test]
+    async fn test_list_subscriptions_round_trip() {
+        let mut transport = MockTransport::new(vec![
+            Response::AckUser { id: 1 },
+            Response::StartSubscriptionList,
+            Response::Subscription {
+                id: 1,
+                url: "gemini://example.com/feed".to_string(),
+            },
+            Response::EndList,
+        ]);
+
+        list_subscriptions(&mut transport, "alice".to_string(), OutputFormat::Table)
+            .await
+            .expect("list_subscriptions should succeed");
+
+        assert_eq!(transport.sent.len(), 2);
+        assert!(matches!(transport.sent[1], Command::ListSubscriptions));
     }
 }
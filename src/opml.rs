@@ -0,0 +1,122 @@
+//! OPML 2.0 import/export.
+
+use anyhow::{format_err, Result};
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::Reader;
+use quick_xml::Writer;
+use std::io::Cursor;
+
+use crate::Subscription;
+
+/// Renders `subscriptions` as an OPML 2.0 document.
+pub fn export(subscriptions: &[Subscription]) -> Result<String> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    let mut opml = BytesStart::new("opml");
+    opml.push_attribute(("version", "2.0"));
+    writer.write_event(Event::Start(opml))?;
+
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("title")))?;
+    writer.write_event(Event::Text(BytesText::new("seymour subscriptions")))?;
+    writer.write_event(Event::End(BytesEnd::new("title")))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+    for subscription in subscriptions {
+        let mut outline = BytesStart::new("outline");
+        outline.push_attribute(("type", "rss"));
+        outline.push_attribute(("text", subscription.url.as_str()));
+        outline.push_attribute(("xmlUrl", subscription.url.as_str()));
+        writer.write_event(Event::Empty(outline))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+
+    writer.write_event(Event::End(BytesEnd::new("opml")))?;
+
+    let bytes = writer.into_inner().into_inner();
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn xml_url(outline: &BytesStart) -> Result<Option<String>> {
+    for attribute in outline.attributes() {
+        let attribute = attribute?;
+        if attribute.key.as_ref() == b"xmlUrl" {
+            return Ok(Some(attribute.unescape_value()?.into_owned()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Parses an OPML document, returning the `xmlUrl` of every `outline`.
+pub fn parse(contents: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(contents);
+    reader.trim_text(true);
+
+    let mut urls = Vec::new();
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(outline) | Event::Empty(outline)
+                if outline.name().as_ref() == b"outline" =>
+            {
+                if let Some(url) = xml_url(&outline)? {
+                    urls.push(url);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    if urls.is_empty() {
+        return Err(format_err!(
+            "no outlines with an xmlUrl attribute found in OPML file"
+        ));
+    }
+
+    Ok(urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_then_parse_round_trips_urls() {
+        let subscriptions = vec![
+            Subscription {
+                id: 1,
+                url: "gemini://example.com/feed".to_string(),
+            },
+            Subscription {
+                id: 2,
+                url: "gemini://example.org/feed".to_string(),
+            },
+        ];
+
+        let document = export(&subscriptions).expect("export should succeed");
+        let urls = parse(&document).expect("parse should succeed");
+
+        assert_eq!(
+            urls,
+            vec![
+                "gemini://example.com/feed".to_string(),
+                "gemini://example.org/feed".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_document_without_outlines() {
+        let document = "<opml version=\"2.0\"><head></head><body></body></opml>";
+
+        let err = parse(document).expect_err("expected an error for an empty document");
+        assert!(err.to_string().contains("no outlines"));
+    }
+}
@@ -0,0 +1,183 @@
+//! TLS transport with trust-on-first-use certificate pinning, since seymour
+//! servers commonly run self-signed certs.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+
+use anyhow::{format_err, Context as _, Result};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, Error as TlsError, ServerName};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+fn known_hosts_path() -> Result<PathBuf> {
+    let dirs = xdg::BaseDirectories::with_prefix("seymour-cli")?;
+    Ok(dirs.place_data_file("known_hosts")?)
+}
+
+fn load_known_hosts(path: &Path) -> Result<HashMap<String, String>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format_err!("failed to read known hosts file at {:?}", path))?;
+
+    let mut hosts = HashMap::new();
+    for line in contents.lines() {
+        if let Some((host_port, fingerprint)) = line.split_once(' ') {
+            hosts.insert(host_port.to_string(), fingerprint.to_string());
+        }
+    }
+
+    Ok(hosts)
+}
+
+fn fingerprint_hex(cert: &Certificate) -> String {
+    let digest = Sha256::digest(&cert.0);
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Pins the fingerprint of the first certificate seen for a given
+/// `host:port` and rejects any later connection whose cert doesn't match.
+struct TofuVerifier {
+    host_port: String,
+    known_hosts_path: PathBuf,
+    known_hosts: Mutex<HashMap<String, String>>,
+}
+
+impl TofuVerifier {
+    fn new(host_port: String, known_hosts_path: PathBuf) -> Result<Self> {
+        let known_hosts = load_known_hosts(&known_hosts_path)?;
+        Ok(Self {
+            host_port,
+            known_hosts_path,
+            known_hosts: Mutex::new(known_hosts),
+        })
+    }
+}
+
+impl ServerCertVerifier for TofuVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        let fingerprint = fingerprint_hex(end_entity);
+
+        let mut known_hosts = self.known_hosts.lock().unwrap();
+
+        match known_hosts.get(&self.host_port) {
+            Some(pinned) if pinned == &fingerprint => Ok(ServerCertVerified::assertion()),
+            Some(pinned) => {
+                println!(
+                    "WARNING: certificate fingerprint for {} has changed!",
+                    self.host_port
+                );
+                println!("  old fingerprint: {}", pinned);
+                println!("  new fingerprint: {}", fingerprint);
+                Err(TlsError::General(format!(
+                    "certificate fingerprint mismatch for {}",
+                    self.host_port
+                )))
+            }
+            None => {
+                known_hosts.insert(self.host_port.clone(), fingerprint.clone());
+                let serialized = known_hosts
+                    .iter()
+                    .map(|(host_port, fingerprint)| format!("{} {}", host_port, fingerprint))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                if let Err(err) = fs::write(&self.known_hosts_path, serialized) {
+                    println!(
+                        "WARNING: failed to persist known host fingerprint: {}",
+                        err
+                    );
+                }
+
+                Ok(ServerCertVerified::assertion())
+            }
+        }
+    }
+}
+
+/// Either a plain `TcpStream` or a TLS-wrapped one.
+pub enum MaybeTlsStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for MaybeTlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            MaybeTlsStream::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeTlsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            MaybeTlsStream::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            MaybeTlsStream::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            MaybeTlsStream::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Wraps `stream` in a TLS session pinned to `host_port`'s certificate.
+pub async fn wrap(stream: TcpStream, host_port: &str) -> Result<MaybeTlsStream> {
+    let verifier = TofuVerifier::new(host_port.to_string(), known_hosts_path()?)?;
+
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+
+    let connector = TlsConnector::from(Arc::new(config));
+
+    let host = host_port
+        .rsplit_once(':')
+        .map(|(host, _port)| host)
+        .unwrap_or(host_port);
+    let server_name = ServerName::try_from(host)
+        .map_err(|_| format_err!("invalid server name for TLS: {}", host))?;
+
+    let tls_stream = connector.connect(server_name, stream).await?;
+
+    Ok(MaybeTlsStream::Tls(Box::new(tls_stream)))
+}
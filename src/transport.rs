@@ -0,0 +1,88 @@
+//! Abstraction over the wire protocol, real or mocked.
+
+use std::collections::VecDeque;
+
+use anyhow::{format_err, Result};
+use async_trait::async_trait;
+use seymour_protocol::{Command, Response};
+use tokio::io::{AsyncWriteExt, BufReader, BufWriter, Lines, ReadHalf, WriteHalf};
+
+use crate::tls::MaybeTlsStream;
+
+#[async_trait]
+pub trait Transport {
+    async fn send(&mut self, command: Command) -> Result<()>;
+    async fn receive(&mut self) -> Result<Response>;
+}
+
+/// A line-delimited reader/writer pair over the (possibly TLS-wrapped)
+/// socket. The writer is buffered so `send` calls can be pipelined.
+pub struct TcpTransport {
+    lines: Lines<BufReader<ReadHalf<MaybeTlsStream>>>,
+    writer: BufWriter<WriteHalf<MaybeTlsStream>>,
+}
+
+impl TcpTransport {
+    pub fn new(
+        lines: Lines<BufReader<ReadHalf<MaybeTlsStream>>>,
+        writer: WriteHalf<MaybeTlsStream>,
+    ) -> Self {
+        Self {
+            lines,
+            writer: BufWriter::new(writer),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for TcpTransport {
+    async fn send(&mut self, command: Command) -> Result<()> {
+        // Buffered until `receive` flushes (or the buffer fills), so sends can
+        // pipeline.
+        Ok(self
+            .writer
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .await?)
+    }
+
+    async fn receive(&mut self) -> Result<Response> {
+        self.writer.flush().await?;
+
+        self.lines
+            .next_line()
+            .await?
+            .ok_or_else(|| format_err!("no line from server"))?
+            .parse()
+            .map_err(Into::into)
+    }
+}
+
+/// An in-memory transport driven by a scripted queue of responses.
+#[derive(Default)]
+pub struct MockTransport {
+    script: VecDeque<Response>,
+    pub sent: Vec<Command>,
+}
+
+impl MockTransport {
+    pub fn new(script: impl Into<VecDeque<Response>>) -> Self {
+        Self {
+            script: script.into(),
+            sent: Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn send(&mut self, command: Command) -> Result<()> {
+        self.sent.push(command);
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> Result<Response> {
+        self.script
+            .pop_front()
+            .ok_or_else(|| format_err!("mock transport script exhausted"))
+    }
+}